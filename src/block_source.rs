@@ -0,0 +1,36 @@
+use std::io;
+
+use async_trait::async_trait;
+use bitcoin::blockdata::block::Header as BlockHeader;
+use bitcoin::BlockHash;
+
+/// An external source of validated block headers that [`HeaderStore::sync`] can pull from.
+///
+/// This exists so a verified header chain can be built without a full P2P stack: an
+/// implementation might talk to Bitcoin Core's JSON-RPC or REST interface, or anything
+/// else that can answer these five questions.
+///
+/// [`HeaderStore::sync`]: crate::storage::HeaderStore::sync
+#[async_trait]
+pub trait BlockSource: Send + Sync {
+    /// Stable identifier for this source, used to track per-source freshness
+    /// (e.g. so a caller can notice a source that stopped announcing and rotate away).
+    fn id(&self) -> &str;
+
+    /// Hash of the source's current best block.
+    async fn best_block_hash(&self) -> io::Result<BlockHash>;
+
+    /// Height of the source's current best block.
+    async fn best_block_height(&self) -> io::Result<u64>;
+
+    /// Fetch the header identified by `hash`.
+    async fn header_by_hash(&self, hash: BlockHash) -> io::Result<BlockHeader>;
+
+    /// Fetch the header at `height` on the source's active chain.
+    async fn header_by_height(&self, height: u64) -> io::Result<BlockHeader>;
+
+    /// Fetch up to `count` headers immediately following `hash`, in chain order.
+    ///
+    /// Returns fewer than `count` headers (possibly zero) once the source's tip is reached.
+    async fn headers_after(&self, hash: BlockHash, count: usize) -> io::Result<Vec<BlockHeader>>;
+}
@@ -0,0 +1,6 @@
+pub mod block_source;
+pub mod rest_source;
+pub mod rpc_source;
+pub mod storage;
+pub mod sync;
+pub mod uint256;
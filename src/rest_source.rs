@@ -0,0 +1,107 @@
+use std::io;
+
+use async_trait::async_trait;
+use bitcoin::blockdata::block::Header as BlockHeader;
+use bitcoin::consensus::encode::deserialize;
+use bitcoin::hashes::hex::HexToArrayError;
+use bitcoin::BlockHash;
+use serde_json::Value;
+
+use crate::block_source::BlockSource;
+
+/// Length of a serialized block header, in bytes.
+const HEADER_LEN: usize = 80;
+
+/// [`BlockSource`] backed by Bitcoin Core's REST interface
+/// (`/rest/headers/<count>/<hash>.bin`, which returns consecutive raw 80-byte
+/// headers, without the length prefixes `HeaderStore` writes to its on-disk log).
+pub struct RestBlockSource {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl RestBlockSource {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        RestBlockSource {
+            client: reqwest::Client::new(),
+            base_url: base_url.into(),
+        }
+    }
+
+    async fn get_bytes(&self, path: &str) -> io::Result<Vec<u8>> {
+        let response = self
+            .client
+            .get(format!("{}{}", self.base_url, path))
+            .send()
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        Ok(bytes.to_vec())
+    }
+
+    async fn chain_info(&self) -> io::Result<Value> {
+        let bytes = self.get_bytes("/rest/chaininfo.json").await?;
+        serde_json::from_slice(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+
+    /// Fetch `hash` itself plus up to `count - 1` headers after it, in chain order.
+    async fn headers_from(&self, hash: BlockHash, count: usize) -> io::Result<Vec<BlockHeader>> {
+        let bytes = self
+            .get_bytes(&format!("/rest/headers/{}/{}.bin", count, hash))
+            .await?;
+        bytes
+            .chunks(HEADER_LEN)
+            .map(|chunk| {
+                deserialize(chunk).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl BlockSource for RestBlockSource {
+    fn id(&self) -> &str {
+        &self.base_url
+    }
+
+    async fn best_block_hash(&self) -> io::Result<BlockHash> {
+        let info = self.chain_info().await?;
+        info.get("bestblockhash")
+            .and_then(Value::as_str)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "chaininfo.json had no bestblockhash"))?
+            .parse()
+            .map_err(|e: HexToArrayError| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+
+    async fn best_block_height(&self) -> io::Result<u64> {
+        let info = self.chain_info().await?;
+        info.get("blocks")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "chaininfo.json had no blocks"))
+    }
+
+    async fn header_by_hash(&self, hash: BlockHash) -> io::Result<BlockHeader> {
+        self.headers_from(hash, 1)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "REST endpoint returned no header"))
+    }
+
+    async fn header_by_height(&self, _height: u64) -> io::Result<BlockHeader> {
+        // Core's REST interface addresses headers by hash only; there is no
+        // `/rest/headers`-by-height endpoint to call here.
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "RestBlockSource cannot look up a header by height",
+        ))
+    }
+
+    async fn headers_after(&self, hash: BlockHash, count: usize) -> io::Result<Vec<BlockHeader>> {
+        let headers = self.headers_from(hash, count + 1).await?;
+        Ok(headers.into_iter().skip(1).collect())
+    }
+}
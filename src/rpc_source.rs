@@ -0,0 +1,132 @@
+use std::io;
+
+use async_trait::async_trait;
+use bitcoin::blockdata::block::Header as BlockHeader;
+use bitcoin::consensus::encode::deserialize;
+use bitcoin::hashes::hex::{FromHex, HexToArrayError};
+use bitcoin::BlockHash;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::block_source::BlockSource;
+
+/// [`BlockSource`] backed by Bitcoin Core's JSON-RPC interface
+/// (`getbestblockhash`, `getblockheader`, `getblockhash`).
+pub struct RpcBlockSource {
+    client: reqwest::Client,
+    url: String,
+    rpc_user: String,
+    rpc_password: String,
+}
+
+impl RpcBlockSource {
+    pub fn new(url: impl Into<String>, rpc_user: impl Into<String>, rpc_password: impl Into<String>) -> Self {
+        RpcBlockSource {
+            client: reqwest::Client::new(),
+            url: url.into(),
+            rpc_user: rpc_user.into(),
+            rpc_password: rpc_password.into(),
+        }
+    }
+
+    async fn call(&self, method: &str, params: Value) -> io::Result<Value> {
+        let body = json!({
+            "jsonrpc": "1.0",
+            "id": "vibecoin",
+            "method": method,
+            "params": params,
+        });
+        let response = self
+            .client
+            .post(&self.url)
+            .basic_auth(&self.rpc_user, Some(&self.rpc_password))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        let payload: RpcResponse = response
+            .json()
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        if let Some(error) = payload.error {
+            return Err(io::Error::new(io::ErrorKind::Other, error.to_string()));
+        }
+        payload
+            .result
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "RPC response had no result"))
+    }
+
+    fn parse_hash(value: &Value) -> io::Result<BlockHash> {
+        value
+            .as_str()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "expected a hex string"))?
+            .parse()
+            .map_err(|e: HexToArrayError| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+
+    fn decode_header(hex: &str) -> io::Result<BlockHeader> {
+        let bytes =
+            Vec::from_hex(hex).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        deserialize(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+
+    async fn block_height(&self, hash: BlockHash) -> io::Result<u64> {
+        let info = self
+            .call("getblockheader", json!([hash.to_string(), true]))
+            .await?;
+        info.get("height")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "getblockheader response had no height"))
+    }
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    result: Option<Value>,
+    error: Option<Value>,
+}
+
+#[async_trait]
+impl BlockSource for RpcBlockSource {
+    fn id(&self) -> &str {
+        &self.url
+    }
+
+    async fn best_block_hash(&self) -> io::Result<BlockHash> {
+        let result = self.call("getbestblockhash", json!([])).await?;
+        Self::parse_hash(&result)
+    }
+
+    async fn best_block_height(&self) -> io::Result<u64> {
+        let hash = self.best_block_hash().await?;
+        self.block_height(hash).await
+    }
+
+    async fn header_by_hash(&self, hash: BlockHash) -> io::Result<BlockHeader> {
+        let hex = self
+            .call("getblockheader", json!([hash.to_string(), false]))
+            .await?;
+        let hex = hex
+            .as_str()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "getblockheader did not return hex"))?;
+        Self::decode_header(hex)
+    }
+
+    async fn header_by_height(&self, height: u64) -> io::Result<BlockHeader> {
+        let hash = self.call("getblockhash", json!([height])).await?;
+        let hash = Self::parse_hash(&hash)?;
+        self.header_by_hash(hash).await
+    }
+
+    async fn headers_after(&self, hash: BlockHash, count: usize) -> io::Result<Vec<BlockHeader>> {
+        let start_height = self.block_height(hash).await?;
+        let mut headers = Vec::with_capacity(count);
+        for height in start_height + 1..=start_height + count as u64 {
+            match self.header_by_height(height).await {
+                Ok(header) => headers.push(header),
+                Err(_) => break, // ran past the node's current tip
+            }
+        }
+        Ok(headers)
+    }
+}
@@ -0,0 +1,249 @@
+use std::io;
+
+use crate::block_source::BlockSource;
+use crate::storage::{HeaderStore, Reorg};
+
+/// Maximum number of headers requested from a [`BlockSource`] per batch.
+const SYNC_BATCH_SIZE: usize = 2000;
+
+impl HeaderStore {
+    /// Drive headers-first sync against `source`.
+    ///
+    /// Walks our locator to find the point where `source`'s chain and ours diverge,
+    /// then pulls headers forward from there in batches, funneling each batch through
+    /// the same validating [`append`](HeaderStore::append) used by every other path
+    /// into the store as soon as it arrives, so a fetch failure partway through a long
+    /// initial sync doesn't throw away everything pulled so far.
+    pub async fn sync(&mut self, source: &dyn BlockSource) -> io::Result<Option<Reorg>> {
+        self.sync_with_batch_size(source, SYNC_BATCH_SIZE).await
+    }
+
+    /// Like [`sync`](Self::sync), but with the per-request batch size as a parameter
+    /// so tests can exercise multi-batch behavior without fetching thousands of headers.
+    pub(crate) async fn sync_with_batch_size(
+        &mut self,
+        source: &dyn BlockSource,
+        batch_size: usize,
+    ) -> io::Result<Option<Reorg>> {
+        let mut fork_point = None;
+        for hash in self.locator_hashes() {
+            if source.header_by_hash(hash).await.is_ok() {
+                fork_point = Some(hash);
+                break;
+            }
+        }
+        let mut cursor = fork_point.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "source shares no common ancestor with our locator",
+            )
+        })?;
+
+        let best_before = self._tip().map(|h| h.block_hash());
+        loop {
+            let batch = source.headers_after(cursor, batch_size).await?;
+            if batch.is_empty() {
+                break;
+            }
+            cursor = batch.last().expect("batch is non-empty").block_hash();
+            let batch_len = batch.len();
+            self.append(&batch)?;
+            // Only mark the source fresh once its headers actually passed validation; a
+            // source that only ever sends rejected/invalid headers must not look fresh.
+            self.record_source_seen(source.id());
+            if batch_len < batch_size {
+                break;
+            }
+        }
+
+        match self._tip().map(|h| h.block_hash()) {
+            Some(new_tip) if Some(new_tip) != best_before => {
+                Ok(Some(self.reorg_between(best_before, new_tip)))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::future::Future;
+    use std::sync::Mutex;
+    use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    use async_trait::async_trait;
+    use bitcoin::blockdata::block::Header as BlockHeader;
+    use bitcoin::blockdata::constants::genesis_block;
+    use bitcoin::hashes::Hash;
+    use bitcoin::{BlockHash, Network};
+
+    use crate::storage::test_support::mine;
+
+    use super::*;
+
+    /// Minimal, no-op-waker executor: the futures under test never actually suspend
+    /// (the fake `BlockSource` below resolves immediately), so a busy poll loop is
+    /// enough without pulling in a real async runtime.
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(std::ptr::null(), &VTABLE)
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        let waker = unsafe { Waker::from_raw(RawWaker::new(std::ptr::null(), &VTABLE)) };
+        let mut cx = Context::from_waker(&waker);
+        let mut fut = Box::pin(fut);
+        loop {
+            if let Poll::Ready(val) = fut.as_mut().poll(&mut cx) {
+                return val;
+            }
+        }
+    }
+
+    enum Response {
+        Headers(Vec<BlockHeader>),
+        Fail,
+    }
+
+    /// A trivial in-process [`BlockSource`] whose `headers_after` responses are canned
+    /// up front, so tests can script exactly what `sync` sees each round trip.
+    struct FakeSource {
+        known: Vec<BlockHeader>,
+        responses: Mutex<VecDeque<Response>>,
+    }
+
+    #[async_trait]
+    impl BlockSource for FakeSource {
+        fn id(&self) -> &str {
+            "fake"
+        }
+
+        async fn best_block_hash(&self) -> io::Result<BlockHash> {
+            self.known
+                .last()
+                .map(|h| h.block_hash())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no known headers"))
+        }
+
+        async fn best_block_height(&self) -> io::Result<u64> {
+            Ok(self.known.len() as u64 - 1)
+        }
+
+        async fn header_by_hash(&self, hash: BlockHash) -> io::Result<BlockHeader> {
+            self.known
+                .iter()
+                .find(|h| h.block_hash() == hash)
+                .cloned()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "unknown hash"))
+        }
+
+        async fn header_by_height(&self, height: u64) -> io::Result<BlockHeader> {
+            self.known
+                .get(height as usize)
+                .cloned()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "unknown height"))
+        }
+
+        async fn headers_after(&self, _hash: BlockHash, _count: usize) -> io::Result<Vec<BlockHeader>> {
+            match self.responses.lock().unwrap().pop_front() {
+                Some(Response::Headers(headers)) => Ok(headers),
+                Some(Response::Fail) => Err(io::Error::new(io::ErrorKind::Other, "simulated network hiccup")),
+                None => Ok(Vec::new()),
+            }
+        }
+    }
+
+    fn temp_file() -> String {
+        let dir = std::env::temp_dir();
+        let name = format!("test_sync_headers_{}.bin", rand::random::<u64>());
+        dir.join(name).to_str().unwrap().to_string()
+    }
+
+    fn mined_chain(len: usize) -> Vec<BlockHeader> {
+        let mut chain = Vec::with_capacity(len);
+        let mut header = genesis_block(Network::Regtest).header;
+        mine(&mut header);
+        chain.push(header.clone());
+        for i in 1..len {
+            let mut next = header.clone();
+            next.prev_blockhash = header.block_hash();
+            next.nonce = i as u32;
+            mine(&mut next);
+            chain.push(next.clone());
+            header = next;
+        }
+        chain
+    }
+
+    #[test]
+    fn rejected_headers_do_not_mark_source_fresh() {
+        let path = temp_file();
+        let mut store = HeaderStore::open(&path, Network::Regtest).unwrap();
+        let chain = mined_chain(2);
+        store.append(&[chain[0].clone()]).unwrap();
+
+        // One known-good header, plus a second that doesn't connect to it: `append`
+        // keeps chain[1] but fails on the second header, so the sync must fail.
+        let mut disconnected = chain[1].clone();
+        disconnected.prev_blockhash = BlockHash::from_raw_hash(Hash::all_zeros());
+        let source = FakeSource {
+            known: vec![chain[0].clone()],
+            responses: Mutex::new(VecDeque::from([Response::Headers(vec![chain[1].clone(), disconnected])])),
+        };
+
+        let result = block_on(store.sync(&source));
+        assert!(result.is_err());
+        assert!(store.source_age("fake").is_none());
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn batches_are_persisted_as_they_arrive() {
+        let path = temp_file();
+        let mut store = HeaderStore::open(&path, Network::Regtest).unwrap();
+        let chain = mined_chain(5);
+        store.append(&[chain[0].clone()]).unwrap();
+
+        // First batch succeeds and should be durably appended even though the second
+        // batch then fails outright.
+        let source = FakeSource {
+            known: vec![chain[0].clone()],
+            responses: Mutex::new(VecDeque::from([
+                Response::Headers(chain[1..3].to_vec()),
+                Response::Fail,
+            ])),
+        };
+
+        let result = block_on(store.sync_with_batch_size(&source, 2));
+        assert!(result.is_err());
+        assert_eq!(store.height(), 3);
+        assert!(store.source_age("fake").is_some());
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn sync_extends_the_chain_across_multiple_batches() {
+        let path = temp_file();
+        let mut store = HeaderStore::open(&path, Network::Regtest).unwrap();
+        let chain = mined_chain(5);
+        store.append(&[chain[0].clone()]).unwrap();
+
+        let source = FakeSource {
+            known: vec![chain[0].clone()],
+            responses: Mutex::new(VecDeque::from([
+                Response::Headers(chain[1..3].to_vec()),
+                Response::Headers(chain[3..5].to_vec()),
+            ])),
+        };
+
+        let reorg = block_on(store.sync_with_batch_size(&source, 2)).unwrap();
+        assert_eq!(store.height(), 5);
+        assert_eq!(reorg.unwrap().connected.len(), 4);
+        assert!(store.source_age("fake").is_some());
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,221 @@
+//! Minimal 256-bit unsigned integer arithmetic.
+//!
+//! `rust-bitcoin`'s `Target` type deliberately doesn't expose general-purpose
+//! arithmetic (only bit shifts for its transition thresholds), but difficulty
+//! retargeting and chainwork accumulation both need to multiply, divide and
+//! add full 256-bit values. This is the shared home for that math.
+
+use std::cmp::Ordering;
+use std::ops::Add;
+
+/// A 256-bit unsigned integer, stored as four big-endian `u64` limbs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct U256([u64; 4]);
+
+impl U256 {
+    pub const ZERO: U256 = U256([0; 4]);
+    pub const ONE: U256 = U256([0, 0, 0, 1]);
+    pub const MAX: U256 = U256([u64::MAX; 4]);
+
+    pub fn from_be_bytes(bytes: [u8; 32]) -> Self {
+        let mut limbs = [0u64; 4];
+        for (i, chunk) in bytes.chunks_exact(8).enumerate() {
+            limbs[i] = u64::from_be_bytes(chunk.try_into().unwrap());
+        }
+        U256(limbs)
+    }
+
+    pub fn to_be_bytes(self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (i, limb) in self.0.iter().enumerate() {
+            bytes[i * 8..i * 8 + 8].copy_from_slice(&limb.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Multiply by a `u64`, saturating at `U256::MAX` on overflow.
+    pub fn saturating_mul_u64(self, rhs: u64) -> Self {
+        let mut limbs = [0u64; 4];
+        let mut carry: u128 = 0;
+        for i in (0..4).rev() {
+            let product = self.0[i] as u128 * rhs as u128 + carry;
+            limbs[i] = product as u64;
+            carry = product >> 64;
+        }
+        if carry != 0 {
+            return U256::MAX;
+        }
+        U256(limbs)
+    }
+
+    /// Divide by a `u64`. Panics on division by zero, matching integer division semantics.
+    pub fn div_u64(self, rhs: u64) -> Self {
+        assert!(rhs != 0, "division by zero");
+        let mut limbs = [0u64; 4];
+        let mut remainder: u128 = 0;
+        for i in 0..4 {
+            let dividend = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (dividend / rhs as u128) as u64;
+            remainder = dividend % rhs as u128;
+        }
+        U256(limbs)
+    }
+
+    /// Full 256-bit division, returning the quotient. Panics on division by zero.
+    pub fn div(self, rhs: U256) -> Self {
+        assert!(rhs != U256::ZERO, "division by zero");
+        if self < rhs {
+            return U256::ZERO;
+        }
+        let mut quotient = U256::ZERO;
+        let mut remainder = U256::ZERO;
+        for bit in (0..256).rev() {
+            remainder = (remainder << 1u32) | U256::from(self.bit(bit));
+            if remainder >= rhs {
+                remainder = remainder.checked_sub(rhs).expect("remainder >= rhs");
+                quotient = quotient.set_bit(bit);
+            }
+        }
+        quotient
+    }
+
+    fn bit(self, index: u32) -> bool {
+        let limb = 3 - (index / 64) as usize;
+        (self.0[limb] >> (index % 64)) & 1 == 1
+    }
+
+    fn set_bit(mut self, index: u32) -> Self {
+        let limb = 3 - (index / 64) as usize;
+        self.0[limb] |= 1 << (index % 64);
+        self
+    }
+
+    fn checked_sub(self, rhs: U256) -> Option<Self> {
+        let mut limbs = [0u64; 4];
+        let mut borrow = 0i128;
+        for i in (0..4).rev() {
+            let diff = self.0[i] as i128 - rhs.0[i] as i128 - borrow;
+            if diff < 0 {
+                limbs[i] = (diff + (1i128 << 64)) as u64;
+                borrow = 1;
+            } else {
+                limbs[i] = diff as u64;
+                borrow = 0;
+            }
+        }
+        if borrow != 0 {
+            None
+        } else {
+            Some(U256(limbs))
+        }
+    }
+}
+
+impl From<bool> for U256 {
+    fn from(bit: bool) -> Self {
+        if bit {
+            U256::ONE
+        } else {
+            U256::ZERO
+        }
+    }
+}
+
+impl PartialOrd for U256 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for U256 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl Add for U256 {
+    type Output = U256;
+
+    fn add(self, rhs: U256) -> U256 {
+        let mut limbs = [0u64; 4];
+        let mut carry = 0u128;
+        for i in (0..4).rev() {
+            let sum = self.0[i] as u128 + rhs.0[i] as u128 + carry;
+            limbs[i] = sum as u64;
+            carry = sum >> 64;
+        }
+        U256(limbs)
+    }
+}
+
+impl std::ops::Not for U256 {
+    type Output = U256;
+
+    fn not(self) -> U256 {
+        let mut limbs = [0u64; 4];
+        for i in 0..4 {
+            limbs[i] = !self.0[i];
+        }
+        U256(limbs)
+    }
+}
+
+impl std::ops::BitOr for U256 {
+    type Output = U256;
+
+    fn bitor(self, rhs: U256) -> U256 {
+        let mut limbs = [0u64; 4];
+        for i in 0..4 {
+            limbs[i] = self.0[i] | rhs.0[i];
+        }
+        U256(limbs)
+    }
+}
+
+impl std::ops::Shl<u32> for U256 {
+    type Output = U256;
+
+    fn shl(self, rhs: u32) -> U256 {
+        assert_eq!(rhs, 1, "only single-bit left shifts are supported");
+        let mut limbs = [0u64; 4];
+        let mut carry = 0u64;
+        for i in (0..4).rev() {
+            limbs[i] = (self.0[i] << 1) | carry;
+            carry = self.0[i] >> 63;
+        }
+        U256(limbs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mul_and_div_roundtrip() {
+        let value = U256::ONE.saturating_mul_u64(12345);
+        let scaled = value.saturating_mul_u64(1000);
+        assert_eq!(scaled.div_u64(1000), value);
+    }
+
+    #[test]
+    fn be_bytes_roundtrip() {
+        let mut bytes = [0u8; 32];
+        bytes[31] = 42;
+        bytes[0] = 1;
+        let value = U256::from_be_bytes(bytes);
+        assert_eq!(value.to_be_bytes(), bytes);
+    }
+
+    #[test]
+    fn div_full_matches_div_u64() {
+        let value = U256::ONE.saturating_mul_u64(1_000_000_000);
+        let divisor = U256::ONE.saturating_mul_u64(7);
+        assert_eq!(value.div(divisor), value.div_u64(7));
+    }
+
+    #[test]
+    fn max_div_one_is_max() {
+        assert_eq!(U256::MAX.div(U256::ONE), U256::MAX);
+    }
+}